@@ -12,34 +12,64 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-pub struct MockAlarm {
+use kernel::common::cells::OptionalCell;
+use kernel::hil::time::AlarmClient;
+
+pub struct MockAlarm<'a> {
     current_time: core::cell::Cell<u32>,
     setpoint: core::cell::Cell<Option<u32>>,
+    client: OptionalCell<&'a dyn AlarmClient>,
 }
 
-impl MockAlarm {
-    pub fn new() -> MockAlarm {
+impl<'a> MockAlarm<'a> {
+    pub fn new() -> MockAlarm<'a> {
         MockAlarm {
             current_time: Default::default(),
-            setpoint: Default::default()
+            setpoint: Default::default(),
+            client: OptionalCell::empty(),
         }
     }
 
     pub fn set_time(&self, new_time: u32) { self.current_time.set(new_time); }
+
+    // Advances virtual time by `delta` tics (wrapping at `max_tics`), firing the client if the
+    // armed setpoint is crossed.
+    pub fn advance(&self, delta: u32) {
+        let old_time = self.current_time.get();
+        let new_time = old_time.wrapping_add(delta);
+        self.current_time.set(new_time);
+        if let Some(setpoint) = self.setpoint.get() {
+            let crossed = if new_time >= old_time {
+                setpoint > old_time && setpoint <= new_time
+            } else {
+                setpoint > old_time || setpoint <= new_time
+            };
+            if crossed {
+                self.setpoint.set(None);
+                self.client.map(|client| client.fired());
+            }
+        }
+    }
+
+    // Unconditionally fires the client if an alarm is currently armed.
+    pub fn fire_now(&self) {
+        if self.setpoint.take().is_some() {
+            self.client.map(|client| client.fired());
+        }
+    }
 }
 
-impl kernel::hil::time::Time for MockAlarm {
+impl<'a> kernel::hil::time::Time for MockAlarm<'a> {
     type Frequency = kernel::hil::time::Freq16MHz;
     fn now(&self) -> u32 { self.current_time.get() }
     fn max_tics(&self) -> u32 { u32::max_value() }
 }
 
-impl<'a> kernel::hil::time::Alarm<'a> for MockAlarm {
+impl<'a> kernel::hil::time::Alarm<'a> for MockAlarm<'a> {
     fn set_alarm(&self, tics: u32) { self.setpoint.set(Some(tics)); }
     fn get_alarm(&self) -> u32 { self.setpoint.get().unwrap_or(0) }
 
-    // Ignored -- the test should manually trigger the client.
-    fn set_client(&'a self, _client: &'a dyn kernel::hil::time::AlarmClient) {}
+    fn set_client(&'a self, client: &'a dyn AlarmClient) { self.client.set(client); }
 
     fn is_enabled(&self) -> bool { self.setpoint.get().is_some() }
 
@@ -49,3 +79,66 @@ impl<'a> kernel::hil::time::Alarm<'a> for MockAlarm {
 
     fn disable(&self) { self.setpoint.set(None); }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::hil::time::Alarm;
+
+    #[derive(Default)]
+    struct MockClient {
+        fired: core::cell::Cell<bool>,
+    }
+
+    impl AlarmClient for MockClient {
+        fn fired(&self) { self.fired.set(true); }
+    }
+
+    #[test]
+    fn advance_fires_client_when_setpoint_is_crossed() {
+        let alarm = MockAlarm::new();
+        let client = MockClient::default();
+        alarm.set_client(&client);
+        alarm.set_alarm(10);
+        alarm.advance(5);
+        assert!(!client.fired.get());
+        alarm.advance(5);
+        assert!(client.fired.get());
+    }
+
+    #[test]
+    fn advance_does_not_fire_before_setpoint() {
+        let alarm = MockAlarm::new();
+        let client = MockClient::default();
+        alarm.set_client(&client);
+        alarm.set_alarm(10);
+        alarm.advance(9);
+        assert!(!client.fired.get());
+    }
+
+    #[test]
+    fn advance_fires_client_across_a_wraparound() {
+        // Arm the alarm near the top of the tic space and advance past `max_tics`, so
+        // `current_time` wraps back around and the setpoint sits below the new time rather
+        // than above it -- the branch `advance` takes when `new_time < old_time`.
+        let alarm = MockAlarm::new();
+        let client = MockClient::default();
+        alarm.set_client(&client);
+        alarm.set_time(u32::max_value() - 4);
+        alarm.set_alarm(5);
+        alarm.advance(3);
+        assert!(!client.fired.get());
+        alarm.advance(8);
+        assert!(client.fired.get());
+    }
+
+    #[test]
+    fn fire_now_fires_an_armed_client_immediately() {
+        let alarm = MockAlarm::new();
+        let client = MockClient::default();
+        alarm.set_client(&client);
+        alarm.set_alarm(1000);
+        alarm.fire_now();
+        assert!(client.fired.get());
+    }
+}